@@ -1,6 +1,7 @@
+use core::borrow::Borrow;
 use core::mem::{self, ManuallyDrop};
 use core::ops::{Deref, DerefMut};
-use stable_deref_trait::StableDeref;
+use stable_deref_trait::{CloneStableDeref, StableDeref};
 
 pub unsafe trait RefClass<'a>: Copy {
     /// Reference which may be reborrowed from this type, given a mutable reference.
@@ -118,6 +119,101 @@ pub struct OwningRef<O, T> {
     borrow: ManuallyDrop<T>,
 }
 
+/// Marker trait for erased owner types.
+///
+/// Implemented for every sized type via a blanket impl, so it carries no
+/// information about the original owner beyond the fact that it used to be
+/// here. This is what lets [`OwningRef::erase_owner`] collapse differently
+/// typed owners into a single `Box<dyn Erased>`.
+pub trait Erased {}
+impl<T> Erased for T {}
+
+impl<O, T> OwningRef<O, T>
+where
+    O: StableDeref + 'static,
+{
+    /// Erase the owner of this `OwningRef`, so that it can be stored
+    /// alongside other `OwningRef`s with differently-typed owners.
+    pub fn erase_owner(mut self) -> OwningRef<Box<dyn Erased + 'static>, T> {
+        unsafe {
+            // Explode `self` into member parts, as in `map`.
+            let owner = ManuallyDrop::take(&mut self.owner);
+            let borrow = ManuallyDrop::take(&mut self.borrow);
+            mem::forget(self);
+
+            OwningRef {
+                owner: ManuallyDrop::new(Box::new(owner)),
+                borrow: ManuallyDrop::new(borrow),
+            }
+        }
+    }
+}
+
+impl<O, T> Clone for OwningRef<O, T>
+where
+    O: CloneStableDeref,
+    T: SharedRef,
+{
+    /// Cheaply clone this `OwningRef` by bumping the owner's refcount.
+    ///
+    /// The owner's stable address is unchanged by `O::clone`, and `T: SharedRef`
+    /// guarantees no exclusive access is held, so the existing `RefClass`
+    /// pointer(s) can simply be byte-copied into the new value.
+    fn clone(&self) -> Self {
+        OwningRef {
+            owner: ManuallyDrop::new((*self.owner).clone()),
+            borrow: ManuallyDrop::new(unsafe { core::ptr::read(&*self.borrow) }),
+        }
+    }
+}
+
+macro_rules! split_impls {
+    ($($($T:ident),*;)*) => {$(
+        impl<Owner, $($T),*> OwningRef<Owner, ($($T,)*)>
+        where
+            Owner: CloneStableDeref,
+            $($T: SharedRef,)*
+        {
+            /// Split a tuple `OwningRef` into independently-ownable
+            /// `OwningRef`s, one per component, each holding its own clone
+            /// of the shared owner.
+            pub fn split(mut self) -> ($(OwningRef<Owner, $T>,)*) {
+                unsafe {
+                    let owner = ManuallyDrop::take(&mut self.owner);
+                    #[allow(non_snake_case)]
+                    let ($($T,)*) = ManuallyDrop::take(&mut self.borrow);
+                    mem::forget(self);
+
+                    ($(
+                        OwningRef {
+                            owner: ManuallyDrop::new(owner.clone()),
+                            borrow: ManuallyDrop::new($T),
+                        },
+                    )*)
+                }
+            }
+        }
+    )*};
+}
+
+split_impls! {
+    A, B;
+    A, B, C;
+    A, B, C, D;
+    A, B, C, D, E;
+    A, B, C, D, E, F;
+    A, B, C, D, E, F, G;
+    A, B, C, D, E, F, G, H;
+    A, B, C, D, E, F, G, H, I;
+    A, B, C, D, E, F, G, H, I, J;
+    A, B, C, D, E, F, G, H, I, J, K;
+    A, B, C, D, E, F, G, H, I, J, K, L;
+    A, B, C, D, E, F, G, H, I, J, K, L, M;
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N;
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O;
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P;
+}
+
 impl<O, T: ?Sized + 'static> OwningRef<O, *mut T>
 where
     O: StableDeref + DerefMut<Target = T>,
@@ -148,6 +244,81 @@ where
     }
 }
 
+impl<O, T: ?Sized + 'static> Deref for OwningRef<O, *const T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { self.borrow.reborrow_const() }
+    }
+}
+
+impl<O, T: ?Sized + 'static> Deref for OwningRef<O, *mut T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { self.borrow.reborrow_const() }
+    }
+}
+
+impl<O, T: ?Sized + 'static> DerefMut for OwningRef<O, *mut T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.borrow.reborrow_mut() }
+    }
+}
+
+macro_rules! deref_forwarding_impls {
+    ($($ptr:ty),* $(,)?) => {$(
+        impl<O, T: ?Sized + 'static> AsRef<T> for OwningRef<O, $ptr> {
+            fn as_ref(&self) -> &T {
+                &**self
+            }
+        }
+
+        impl<O, T: ?Sized + 'static> Borrow<T> for OwningRef<O, $ptr> {
+            fn borrow(&self) -> &T {
+                &**self
+            }
+        }
+
+        impl<O, T: ?Sized + 'static + PartialEq> PartialEq for OwningRef<O, $ptr> {
+            fn eq(&self, other: &Self) -> bool {
+                (**self).eq(&**other)
+            }
+        }
+        impl<O, T: ?Sized + 'static + Eq> Eq for OwningRef<O, $ptr> {}
+
+        impl<O, T: ?Sized + 'static + PartialOrd> PartialOrd for OwningRef<O, $ptr> {
+            fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+                (**self).partial_cmp(&**other)
+            }
+        }
+        impl<O, T: ?Sized + 'static + Ord> Ord for OwningRef<O, $ptr> {
+            fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+                (**self).cmp(&**other)
+            }
+        }
+
+        impl<O, T: ?Sized + 'static + core::hash::Hash> core::hash::Hash for OwningRef<O, $ptr> {
+            fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+                (**self).hash(state)
+            }
+        }
+
+        impl<O, T: ?Sized + 'static + core::fmt::Debug> core::fmt::Debug for OwningRef<O, $ptr> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                core::fmt::Debug::fmt(&**self, f)
+            }
+        }
+        impl<O, T: ?Sized + 'static + core::fmt::Display> core::fmt::Display for OwningRef<O, $ptr> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                core::fmt::Display::fmt(&**self, f)
+            }
+        }
+    )*};
+}
+
+// Restricted to the non-tuple pointer classes so these don't conflict with
+// the multi-reference (tuple) `RefClass`/`HasRefClass` impls above.
+deref_forwarding_impls!(*const T, *mut T);
+
 impl<O, T> OwningRef<O, T>
 where
     for<'a> T: RefClass<'a>,
@@ -264,6 +435,98 @@ impl<O, T> OwningRef<O, T> {
     }
 }
 
+/// A smart pointer built from an owner and a handle which was constructed
+/// from, and borrows from, that owner.
+///
+/// Unlike `OwningRef`, the dependent value is not restricted to a
+/// reborrowable `RefClass` reference: it may be any `StableDeref` type, such
+/// as a lock guard. This makes it possible to build something like
+/// `OwningHandle<Arc<Mutex<T>>, MutexGuard<'_, T>>`, letting the guard be
+/// returned from a function and moved around together with the lock that
+/// keeps it alive.
+pub struct OwningHandle<O, H> {
+    owner: ManuallyDrop<O>,
+    handle: ManuallyDrop<H>,
+}
+
+impl<O, H> Deref for OwningHandle<O, H>
+where
+    H: Deref,
+{
+    type Target = H::Target;
+    fn deref(&self) -> &H::Target {
+        &self.handle
+    }
+}
+
+impl<O, H> DerefMut for OwningHandle<O, H>
+where
+    H: DerefMut,
+{
+    fn deref_mut(&mut self) -> &mut H::Target {
+        &mut self.handle
+    }
+}
+
+impl<O, H> OwningHandle<O, H>
+where
+    O: StableDeref,
+    H: StableDeref,
+{
+    /// Create a new `OwningHandle` for an owner, and a handle constructed
+    /// from a stable pointer into that owner.
+    ///
+    /// `f` is handed a raw pointer into the owner's stable target, and must
+    /// return a handle which borrows from it. The handle is then treated as
+    /// valid for as long as this `OwningHandle` is alive; `f` must not allow
+    /// the pointer it is given to escape that borrow in any other way.
+    pub fn new_with<F>(owner: O, f: F) -> Self
+    where
+        F: FnOnce(*const O::Target) -> H,
+    {
+        let handle = f(&*owner as *const O::Target);
+        OwningHandle {
+            owner: ManuallyDrop::new(owner),
+            handle: ManuallyDrop::new(handle),
+        }
+    }
+
+    /// Try to create a new `OwningHandle`, for cases where constructing the
+    /// handle may fail. See `new_with` for the safety requirements on `f`.
+    pub fn try_new<F, E>(owner: O, f: F) -> Result<Self, E>
+    where
+        F: FnOnce(*const O::Target) -> Result<H, E>,
+    {
+        let handle = f(&*owner as *const O::Target)?;
+        Ok(OwningHandle {
+            owner: ManuallyDrop::new(owner),
+            handle: ManuallyDrop::new(handle),
+        })
+    }
+
+    /// Drop the handle, and unwrap to the underlying owner.
+    pub fn into_owner(mut self) -> O {
+        unsafe {
+            ManuallyDrop::drop(&mut self.handle);
+            let owner = ManuallyDrop::take(&mut self.owner);
+            mem::forget(self);
+            owner
+        }
+    }
+}
+
+impl<O, H> Drop for OwningHandle<O, H> {
+    fn drop(&mut self) {
+        unsafe {
+            // Drop the handle before the owner, exactly as `into_owner` does,
+            // so that ordinary scope exit releases the handle's borrow (e.g.
+            // a lock guard) before the owner it borrows from goes away.
+            ManuallyDrop::drop(&mut self.handle);
+            ManuallyDrop::drop(&mut self.owner);
+        }
+    }
+}
+
 #[test]
 fn compile_checks() {
     #[allow(dead_code)]
@@ -290,6 +553,141 @@ fn compile_checks() {
     // }
 }
 
+#[test]
+fn erase_owner_checks() {
+    use std::rc::Rc;
+
+    fn as_str(s: &String) -> &str {
+        &s[..]
+    }
+
+    // `erase_owner` only requires `O: StableDeref + 'static`, so owners of
+    // completely unrelated concrete types can share a `Vec` once erased.
+    let shared = Rc::new(String::from("world"));
+    let a = OwningRef::new_shared(Box::new(String::from("hello"))).map(as_str);
+    let b = OwningRef::new_shared(Rc::clone(&shared)).map(as_str);
+    assert_eq!(Rc::strong_count(&shared), 2);
+
+    let erased: Vec<OwningRef<Box<dyn Erased>, *const str>> =
+        vec![a.erase_owner(), b.erase_owner()];
+    assert_eq!(erased[0].borrow(), "hello");
+    assert_eq!(erased[1].borrow(), "world");
+    assert_eq!(Rc::strong_count(&shared), 2);
+
+    // Dropping the erased owner must still run the original owner's
+    // destructor (here, decrementing the `Rc`'s refcount) even though its
+    // concrete type has been erased into `Box<dyn Erased>`.
+    for owning_ref in erased {
+        owning_ref.into_owner();
+    }
+    assert_eq!(Rc::strong_count(&shared), 1);
+}
+
+#[test]
+fn deref_checks() {
+    let a = OwningRef::new_shared(Box::new(String::from("hello")));
+    let b = OwningRef::new_shared(Box::new(String::from("hello")));
+    assert_eq!(&*a, "hello");
+    assert_eq!(a.as_ref(), "hello");
+    assert_eq!(a, b);
+    assert_eq!(format!("{:?}", a), "\"hello\"");
+    assert_eq!(format!("{}", a), "hello");
+
+    let mut c = OwningRef::new(Box::new(String::from("hello")));
+    c.push('!');
+    assert_eq!(&*c, "hello!");
+
+    // `Borrow<str>` lets an `OwningRef` be used directly as a `HashMap` key.
+    fn as_str(s: &String) -> &str {
+        &s[..]
+    }
+    let mut map = std::collections::HashMap::new();
+    map.insert(
+        OwningRef::new_shared(Box::new(String::from("hello"))).map(as_str),
+        1,
+    );
+    assert_eq!(map.get("hello"), Some(&1));
+}
+
+#[test]
+fn clone_checks() {
+    use std::rc::Rc;
+
+    fn second_byte(v: &Vec<u8>) -> &u8 {
+        &v[1]
+    }
+
+    let owner = Rc::new(vec![10u8, 20, 30]);
+    let r = OwningRef::new_shared(Rc::clone(&owner)).map(second_byte);
+    assert_eq!(*r.borrow(), 20);
+    assert_eq!(Rc::strong_count(&owner), 2);
+
+    let r2 = r.clone();
+    assert_eq!(*r2.borrow(), 20);
+    assert_eq!(Rc::strong_count(&owner), 3);
+
+    // `OwningRef` only releases its owner through `into_owner`; the returned
+    // `Rc` is dropped immediately here since it's not bound to anything.
+    r.into_owner();
+    assert_eq!(Rc::strong_count(&owner), 2);
+    r2.into_owner();
+    assert_eq!(Rc::strong_count(&owner), 1);
+}
+
+#[test]
+fn split_checks() {
+    use std::rc::Rc;
+
+    fn both_fields(t: &(String, String)) -> (&String, &String) {
+        (&t.0, &t.1)
+    }
+
+    let owner = Rc::new((String::from("hello"), String::from("world")));
+    let combined = OwningRef::new_shared(Rc::clone(&owner)).map(both_fields);
+    assert_eq!(Rc::strong_count(&owner), 2);
+
+    let (a, b) = combined.split();
+    assert_eq!(a.borrow().as_str(), "hello");
+    assert_eq!(b.borrow().as_str(), "world");
+    assert_eq!(Rc::strong_count(&owner), 3);
+
+    // `OwningRef` only releases its owner through `into_owner`; the returned
+    // `Rc` is dropped immediately here since it's not bound to anything.
+    a.into_owner();
+    assert_eq!(Rc::strong_count(&owner), 2);
+    b.into_owner();
+    assert_eq!(Rc::strong_count(&owner), 1);
+}
+
+#[test]
+fn owning_handle_checks() {
+    use std::sync::{Arc, Mutex};
+
+    let mutex = Arc::new(Mutex::new(1u32));
+    let other = Arc::clone(&mutex);
+
+    {
+        let mut handle =
+            OwningHandle::new_with(Arc::clone(&mutex), |m| unsafe { (*m).lock().unwrap() });
+        *handle += 1;
+        assert_eq!(*handle, 2);
+        // Dropping `handle` here must release the lock, not just leak it.
+    }
+    assert_eq!(
+        *other
+            .try_lock()
+            .expect("OwningHandle's Drop impl should release the lock"),
+        2
+    );
+
+    let handle =
+        OwningHandle::try_new::<_, ()>(Arc::clone(&mutex), |m| unsafe { (*m).lock().map_err(|_| ()) })
+            .unwrap();
+    assert_eq!(*handle, 2);
+    drop(handle);
+    assert!(other.try_lock().is_ok());
+}
+
 /// Traits used for `OwningRef::[try_]map`
 pub mod map {
     use super::*;